@@ -1,17 +1,265 @@
-use reqwest::Client;
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
 
+/// Credentials and endpoint selection for the OpenAI (or OpenAI-compatible)
+/// chat completions API.
 pub struct OpenAICreds {
     pub api_key: String,
     pub org_id: Option<String>,
     pub project_id: Option<String>,
+    /// Chat completions endpoint. Defaults to OpenAI's, but can point at a
+    /// self-hosted OpenAI-compatible server.
+    pub base_url: String,
+    pub model: String,
 }
 
+impl OpenAICreds {
+    pub fn new(api_key: String) -> Self {
+        OpenAICreds {
+            api_key,
+            org_id: None,
+            project_id: None,
+            base_url: "https://api.openai.com/v1/chat/completions".to_string(),
+            model: "gpt-3.5-turbo".to_string(),
+        }
+    }
+}
+
+/// Where to reach a self-hosted or local model process, for providers that
+/// don't talk to the OpenAI API.
+pub struct ProviderConfig {
+    pub binary_path: String,
+    pub args: Vec<String>,
+}
+
+/// A backend capable of completing a validation prompt. Lets validation
+/// logic stay agnostic to whether it's talking to OpenAI, a self-hosted
+/// OpenAI-compatible server, or a local model subprocess.
+#[async_trait]
+pub trait ValidationProvider: Send + Sync {
+    async fn complete(&self, prompt: String) -> Result<String, Box<dyn Error>>;
+}
+
+/// Talks to the OpenAI chat completions API (or an OpenAI-compatible
+/// endpoint configured via `OpenAICreds::base_url`).
+pub struct OpenAIProvider {
+    pub creds: OpenAICreds,
+    pub config: GptConfig,
+    client: Client,
+}
+
+impl OpenAIProvider {
+    pub fn new(creds: OpenAICreds, config: GptConfig) -> Self {
+        OpenAIProvider {
+            creds,
+            config,
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ValidationProvider for OpenAIProvider {
+    async fn complete(&self, prompt: String) -> Result<String, Box<dyn Error>> {
+        let chat_request = ChatRequest {
+            model: self.creds.model.clone(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: prompt,
+            }],
+            response_format: ResponseFormat {
+                kind: "json_object".to_string(),
+            },
+            stream: self.config.stream,
+        };
+
+        let response = send_with_retry(
+            &self.client,
+            &self.creds,
+            &chat_request,
+            self.config.max_retries,
+        )
+        .await?;
+
+        if self.config.stream {
+            consume_sse_stream(response, self.config.timeout).await
+        } else {
+            let chat_response = tokio::time::timeout(self.config.timeout, response.json::<ChatResponse>())
+                .await
+                .map_err(|_| "timed out waiting for OpenAI response")??;
+            Ok(chat_response
+                .choices
+                .first()
+                .map_or_else(String::new, |choice| choice.message.content.clone()))
+        }
+    }
+}
+
+/// Spawns a local model binary and exchanges the prompt/response over its
+/// stdin/stdout pipes, for air-gapped or cost-sensitive setups that can't
+/// call out to a hosted API.
+pub struct LocalProcessProvider {
+    config: ProviderConfig,
+}
+
+impl LocalProcessProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        LocalProcessProvider { config }
+    }
+}
+
+#[async_trait]
+impl ValidationProvider for LocalProcessProvider {
+    async fn complete(&self, prompt: String) -> Result<String, Box<dyn Error>> {
+        let mut child = Command::new(&self.config.binary_path)
+            .args(&self.config.args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+
+        let mut stdin = child.stdin.take().ok_or("child process has no stdin")?;
+        let mut stdout = child.stdout.take().ok_or("child process has no stdout")?;
+
+        // The prompt can exceed the pipe buffer, so the write and the read
+        // must run concurrently: a model that emits output before stdin is
+        // fully drained would otherwise deadlock against the unread stdout
+        // pipe while the parent blocks on a full stdin pipe.
+        // stdin is closed as soon as the prompt is written, so a model that
+        // reads its input to EOF before answering sees the end of the prompt.
+        let mut output = String::new();
+        let write = async move {
+            stdin.write_all(prompt.as_bytes()).await?;
+            drop(stdin);
+            Ok::<(), std::io::Error>(())
+        };
+        let read = stdout.read_to_string(&mut output);
+        let (write_result, read_result) = tokio::join!(write, read);
+        write_result?;
+        read_result?;
+
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(format!("local model process exited with {}", status).into());
+        }
+
+        Ok(output)
+    }
+}
+
+/// A finding's severity, ordered from least to most urgent so callers can
+/// threshold on it (`severity >= min_severity`) instead of matching exact
+/// strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Info => "Info",
+            Severity::Low => "Low",
+            Severity::Medium => "Medium",
+            Severity::High => "High",
+            Severity::Critical => "Critical",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Error returned when a string doesn't match one of the known severity names.
+#[derive(Debug)]
+pub struct ParseSeverityError(String);
+
+impl fmt::Display for ParseSeverityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown severity: {}", self.0)
+    }
+}
+
+impl Error for ParseSeverityError {}
+
+impl std::str::FromStr for Severity {
+    type Err = ParseSeverityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Info" => Ok(Severity::Info),
+            "Low" => Ok(Severity::Low),
+            "Medium" => Ok(Severity::Medium),
+            "High" => Ok(Severity::High),
+            "Critical" => Ok(Severity::Critical),
+            other => Err(ParseSeverityError(other.to_string())),
+        }
+    }
+}
+
+/// A single static-analysis finding as reported by the scanner, before GPT
+/// validation. `vulnerability_id` is a stable identifier (e.g. a rule ID)
+/// suitable for keying downstream artifacts like VEX records, distinct from
+/// `title`, the free-text human-readable description shown to the model.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub line: usize,
+    pub vulnerability_id: String,
+    pub severity: Severity,
+    pub title: String,
+}
+
+/// Tunables for how `OpenAIProvider` talks to the model: how hard to retry
+/// on rate limits/transient errors, whether to stream the response via SSE
+/// instead of waiting for the full completion, and how long to wait before
+/// giving up on a stalled request or stream.
+#[derive(Debug, Clone)]
+pub struct GptConfig {
+    pub max_retries: u32,
+    pub stream: bool,
+    /// In non-streaming mode, the limit on reading the response body once a
+    /// successful response has arrived; sending the request and its retries
+    /// are not bounded by it. In streaming mode, the limit on the wait for
+    /// each SSE chunk; on expiry, streaming mode returns whatever partial
+    /// content had been accumulated so far rather than hanging.
+    pub timeout: Duration,
+}
+
+impl Default for GptConfig {
+    fn default() -> Self {
+        GptConfig {
+            max_retries: 5,
+            stream: false,
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Base delay for the first retry; doubles on each subsequent attempt.
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound on the computed backoff, regardless of attempt count.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
 #[derive(Serialize)]
 struct ChatRequest {
     model: String,
     messages: Vec<Message>,
+    response_format: ResponseFormat,
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: String,
 }
 
 #[derive(Serialize)]
@@ -35,117 +283,443 @@ struct MessageContent {
     content: String,
 }
 
+#[derive(Deserialize)]
+struct ChatStreamChunk {
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    delta: StreamDelta,
+}
+
+#[derive(Deserialize, Default)]
+struct StreamDelta {
+    #[serde(default)]
+    content: String,
+}
+
+/// Machine-parseable classification of a single finding, as returned by the
+/// validation model instead of being guessed from free-form prose.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Verdict {
+    Valid,
+    FalsePositive,
+    /// The model's confidence was too low, or its reply couldn't be parsed as JSON.
+    Uncertain,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Valid => write!(f, "Valid"),
+            Verdict::FalsePositive => write!(f, "False positive"),
+            Verdict::Uncertain => write!(f, "Uncertain"),
+        }
+    }
+}
+
+/// One finding's verdict as returned by the model, deserialized directly from
+/// its JSON-mode response rather than sniffed out of prose.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindingVerdict {
+    pub line: usize,
+    /// Identifies which finding this verdict belongs to when more than one
+    /// finding lands on the same line (e.g. two rules firing on one line).
+    pub vulnerability_id: String,
+    pub verdict: Verdict,
+    pub confidence: f32,
+    pub rationale: String,
+    /// A concrete suggested code fix, set only when `verdict` is `Valid` and
+    /// the model supplied one.
+    #[serde(default)]
+    pub fix: Option<String>,
+}
+
+/// The model's reply to a single-finding validation prompt, missing the
+/// `line` and `vulnerability_id` (already known to the caller) compared to
+/// `FindingVerdict`.
+#[derive(Debug, Deserialize)]
+struct SingleVerdictResponse {
+    verdict: Verdict,
+    confidence: f32,
+    rationale: String,
+    #[serde(default)]
+    fix: Option<String>,
+}
+
+/// Minimum confidence below which a `Valid`/`FalsePositive` verdict is
+/// downgraded to `Uncertain` rather than trusted outright.
+const MIN_CONFIDENCE: f32 = 0.4;
+
+/// `validate_all_severities` as a threshold: every finding is Info or above,
+/// so thresholding at `Info` reproduces the old "validate everything" mode.
+pub const VALIDATE_ALL_SEVERITIES: Severity = Severity::Info;
+
+/// Validates every finding for a file against the given provider, one
+/// request per finding, aggregating the per-finding verdicts (and any
+/// suggested fixes) returned by `validate_finding_with_gpt`. Only findings
+/// at or above `min_severity` are sent for validation.
 pub async fn validate_vulnerabilities_with_gpt(
-    openai_creds: &OpenAICreds,
-    findings_by_file: &[(usize, String, String, String)],
+    provider: &dyn ValidationProvider,
+    findings_by_file: &[Finding],
     file_content: &str,
     language: &str,
-    validate_all_severities: bool,
-) -> Result<(String, String), Box<dyn Error>> {
-    let client = Client::new();
-
-    let mut findings_list = String::new();
-    for (line_number, vulnerability_id, severity, _) in findings_by_file {
-        if validate_all_severities || severity == "Critical" || severity == "High" {
-            findings_list.push_str(&format!("line {}: {}\n", line_number, vulnerability_id));
+    min_severity: Severity,
+) -> Result<Vec<FindingVerdict>, Box<dyn Error>> {
+    let mut results = Vec::new();
+
+    for finding in findings_by_file {
+        if finding.severity < min_severity {
+            continue;
         }
+
+        let verdict = validate_finding_with_gpt(provider, finding, file_content, language).await?;
+
+        results.push(verdict);
     }
 
+    Ok(results)
+}
+
+/// Validates a single finding against the given provider, passing its title,
+/// severity, line number, the flagged line of code, and the full file for
+/// context. Returns the verdict plus a concrete suggested fix when the
+/// verdict is `Valid`.
+pub async fn validate_finding_with_gpt(
+    provider: &dyn ValidationProvider,
+    finding: &Finding,
+    file_content: &str,
+    language: &str,
+) -> Result<FindingVerdict, Box<dyn Error>> {
+    let flagged_line = file_content
+        .lines()
+        .nth(finding.line.saturating_sub(1))
+        .unwrap_or("");
+
+    let schema_instructions = "Respond with ONLY a JSON object, no other text, of the shape \
+        {\"verdict\": \"Valid\" | \"FalsePositive\" | \"Uncertain\", \"confidence\": <0.0-1.0>, \
+        \"rationale\": \"<short explanation>\", \"fix\": \"<suggested code fix>\" or null}. Only \
+        populate \"fix\" when \"verdict\" is \"Valid\".";
+
+    let finding_description = format!(
+        "Finding: {}\nSeverity: {}\nLine {}: {}",
+        finding.title, finding.severity, finding.line, flagged_line
+    );
+
     let prompt = match language {
         "Rust" => format!(
-            "A SAST tool detects potential Rust vulnerabilities in the following file:\n\nSource code:\n{}\n\nFindings list:\n{}\n\nAre these valid vulnerabilities or false positives? Provide an explanation.",
-            file_content, findings_list
+            "A SAST tool detects a potential Rust vulnerability in the following file:\n\nSource code:\n{}\n\n{}\n\nIs this a valid vulnerability or a false positive?\n\n{}",
+            file_content, finding_description, schema_instructions
         ),
         "Solidity-Ethereum" => format!(
-            "A SAST tool detects potential Solidity vulnerabilities in the following file:\n\nSource code:\n{}\n\nFindings list:\n{}\n\nAre these valid vulnerabilities or false positives? Provide an explanation.",
-            file_content, findings_list
+            "A SAST tool detects a potential Solidity vulnerability in the following file:\n\nSource code:\n{}\n\n{}\n\nIs this a valid vulnerability or a false positive?\n\n{}",
+            file_content, finding_description, schema_instructions
         ),
         _ => return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Unsupported language"))),
     };
 
-    let chat_request = ChatRequest {
-        model: "gpt-3.5-turbo".to_string(),
-        messages: vec![Message {
-            role: "user".to_string(),
-            content: prompt,
-        }],
-    };
+    let text = provider.complete(prompt).await?;
 
-    let mut response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", openai_creds.api_key));
-    if let Some(org) = &openai_creds.org_id {
-        response = response.header("OpenAI-Organization", org);
+    Ok(parse_single_verdict(
+        finding.line,
+        &finding.vulnerability_id,
+        &text,
+    ))
+}
+
+/// Sends the chat request, retrying on 429 and transient 5xx responses with
+/// exponential backoff (honoring `Retry-After` when the server sends one)
+/// until `max_retries` retries (so up to `max_retries + 1` requests in all)
+/// have been made.
+async fn send_with_retry(
+    client: &Client,
+    openai_creds: &OpenAICreds,
+    chat_request: &ChatRequest,
+    max_retries: u32,
+) -> Result<Response, Box<dyn Error>> {
+    let mut attempt = 0;
+    let mut backoff = BASE_BACKOFF;
+
+    loop {
+        let mut request = client
+            .post(&openai_creds.base_url)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", openai_creds.api_key));
+        if let Some(org) = &openai_creds.org_id {
+            request = request.header("OpenAI-Organization", org);
+        }
+        if let Some(project) = &openai_creds.project_id {
+            request = request.header("OpenAI-Project", project);
+        }
+        let response = request.json(chat_request).send().await?;
+
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let retryable = matches!(
+            response.status(),
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        if !retryable || attempt >= max_retries {
+            return Err(Box::new(std::io::Error::other(
+                "Failed to get a valid response from OpenAI",
+            )));
+        }
+
+        let wait = retry_after(&response).unwrap_or(backoff).min(MAX_BACKOFF);
+        tokio::time::sleep(wait).await;
+
+        attempt += 1;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
     }
-    if let Some(project) = &openai_creds.project_id {
-        response = response.header("OpenAI-Project", project);
+}
+
+/// Reads a `Retry-After` header (seconds) off a response, if present.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get("Retry-After")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Consumes a `"stream": true` chat completion as Server-Sent Events,
+/// accumulating each chunk's `choices[].delta.content` into the full reply.
+///
+/// If no chunk arrives within `timeout` (e.g. a stalled connection that
+/// never sends `[DONE]`), returns whatever partial content had been
+/// accumulated so far instead of hanging indefinitely. The limit applies to
+/// each wait for a chunk, not to the stream as a whole.
+async fn consume_sse_stream(response: Response, timeout: Duration) -> Result<String, Box<dyn Error>> {
+    let mut content = String::new();
+    let mut buffer = String::new();
+    let mut stream = response.bytes_stream();
+
+    loop {
+        let next = match tokio::time::timeout(timeout, stream.next()).await {
+            Ok(next) => next,
+            Err(_) => return Ok(content),
+        };
+        let Some(chunk) = next else {
+            break;
+        };
+        let chunk = chunk?;
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+        while let Some(pos) = buffer.find('\n') {
+            let line = buffer[..pos].trim().to_string();
+            buffer.drain(..=pos);
+
+            let Some(data) = line.strip_prefix("data: ") else {
+                continue;
+            };
+            if data == "[DONE]" {
+                return Ok(content);
+            }
+            if let Ok(chunk) = serde_json::from_str::<ChatStreamChunk>(data) {
+                if let Some(choice) = chunk.choices.first() {
+                    content.push_str(&choice.delta.content);
+                }
+            }
+        }
     }
-    let response = response.json(&chat_request).send().await?;
 
-    if response.status().is_success() {
-        let chat_response = response.json::<ChatResponse>().await?;
-        let text = chat_response
-            .choices
-            .get(0)
-            .map_or_else(|| "", |choice| &choice.message.content);
+    Ok(content)
+}
 
-        let status = analyze_response_text(&text);
+/// Parses the model's JSON-mode reply to a single-finding prompt. Malformed
+/// JSON or an empty reply no longer defaults to "False positive" — it
+/// becomes `Uncertain` with the raw text preserved as the rationale so
+/// callers can still inspect what the model said.
+fn parse_single_verdict(line_number: usize, vulnerability_id: &str, text: &str) -> FindingVerdict {
+    if text.is_empty() {
+        return FindingVerdict {
+            line: line_number,
+            vulnerability_id: vulnerability_id.to_string(),
+            verdict: Verdict::Uncertain,
+            confidence: 0.0,
+            rationale: String::new(),
+            fix: None,
+        };
+    }
 
-        Ok((status.to_string(), "".to_string()))
-    } else {
-        Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Failed to get a valid response from OpenAI",
-        )))
-    }
-}
-
-fn analyze_response_text(text: &str) -> &str {
-    if text.contains("not a vulnerability")
-        || text.contains("is not a valid vulnerability")
-        || text
-            .to_lowercase()
-            .contains("appears to be a false positive")
-        || text.to_lowercase().contains("is no vulnerability present")
-        || text.to_lowercase().contains("is a false positive")
-        || text.to_lowercase().contains("likely a false positive")
-        || text.to_lowercase().contains("may be a false positive")
-        || text.to_lowercase().contains("seems to be a false positive")
-        || text.to_lowercase().contains("most likely a false positive")
-        || text
-            .to_lowercase()
-            .contains("does not contain a vulnerability")
-        || text
-            .to_lowercase()
-            .contains("not appear to have a potential vulnerability")
-        || text
-            .to_lowercase()
-            .contains("does not seem to have any obvious vulnerability")
-        || text
-            .to_lowercase()
-            .contains("does not introduce a vulnerability")
-        || text
-            .to_lowercase()
-            .contains("not suggest any security issues")
-        || text
-            .to_lowercase()
-            .contains("does not appear to be vulnerable")
-        || text
-            .to_lowercase()
-            .contains("does not appear to have any clear vulnerability")
-        || text
-            .to_lowercase()
-            .contains("does not appear to have any potential vulnerability")
-        || text.to_lowercase().contains("is not valid in this case")
-        || text.to_lowercase().contains("does not appear to be valid")
-        || text
-            .to_lowercase()
-            .contains("does not appear to contain any potential vulnerability")
-        || text.is_empty()
-    {
-        "False positive"
+    match serde_json::from_str::<SingleVerdictResponse>(text) {
+        Ok(response) => {
+            let verdict = if response.confidence < MIN_CONFIDENCE && response.verdict != Verdict::Uncertain {
+                Verdict::Uncertain
+            } else {
+                response.verdict
+            };
+            // Only a confirmed `Valid` verdict may carry a suggested fix —
+            // a finding downgraded to `Uncertain` hasn't been confirmed.
+            let fix = if verdict == Verdict::Valid {
+                response.fix
+            } else {
+                None
+            };
+            FindingVerdict {
+                line: line_number,
+                vulnerability_id: vulnerability_id.to_string(),
+                verdict,
+                confidence: response.confidence,
+                rationale: response.rationale,
+                fix,
+            }
+        }
+        Err(_) => FindingVerdict {
+            line: line_number,
+            vulnerability_id: vulnerability_id.to_string(),
+            verdict: Verdict::Uncertain,
+            confidence: 0.0,
+            rationale: text.to_string(),
+            fix: None,
+        },
+    }
+}
+
+/// A single CycloneDX `vulnerabilities[]` entry whose `analysis` record
+/// describes the exploitability of one finding, as defined by the VEX
+/// extension to the CycloneDX schema.
+#[derive(Debug, Clone, Serialize)]
+pub struct VexStatement {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub id: String,
+    pub analysis: VexAnalysis,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VexAnalysis {
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub justification: Option<String>,
+    pub detail: String,
+}
+
+/// Turns GPT validation verdicts into CycloneDX VEX analysis records keyed
+/// by each finding's `vulnerability_id`, so downstream SBOM/vuln tooling can
+/// consume the analyzer's triage instead of the ad-hoc status strings.
+pub fn to_cyclonedx_vex(
+    findings_by_file: &[Finding],
+    verdicts: &[FindingVerdict],
+) -> serde_json::Value {
+    let statements: Vec<VexStatement> = findings_by_file
+        .iter()
+        .filter_map(|finding| {
+            let verdict = verdicts.iter().find(|v| {
+                v.line == finding.line && v.vulnerability_id == finding.vulnerability_id
+            })?;
+            let (state, justification) = match verdict.verdict {
+                Verdict::Valid => ("exploitable", None),
+                Verdict::FalsePositive => {
+                    ("not_affected", false_positive_justification(&verdict.rationale))
+                }
+                Verdict::Uncertain => ("in_triage", None),
+            };
+            Some(VexStatement {
+                bom_ref: format!("{}-{}", finding.vulnerability_id, finding.line),
+                id: finding.vulnerability_id.clone(),
+                analysis: VexAnalysis {
+                    state: state.to_string(),
+                    justification,
+                    detail: verdict.rationale.clone(),
+                },
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "vulnerabilities": statements,
+    })
+}
+
+/// Picks a CycloneDX VEX justification for a `FalsePositive` verdict from
+/// the model's rationale, rather than asserting a specific justification it
+/// never gave. Falls back to `None` when the rationale doesn't clearly
+/// indicate one of the standard justification codes.
+fn false_positive_justification(rationale: &str) -> Option<String> {
+    let lower = rationale.to_lowercase();
+    if lower.contains("not present") || lower.contains("does not exist") {
+        Some("code_not_present".to_string())
+    } else if lower.contains("not reachable") || lower.contains("unreachable") {
+        Some("code_not_reachable".to_string())
     } else {
-        "Valid"
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_single_verdict_downgrades_low_confidence_to_uncertain() {
+        let text = r#"{"verdict":"Valid","confidence":0.1,"rationale":"looks risky","fix":"sanitize input"}"#;
+        let verdict = parse_single_verdict(42, "RULE-1", text);
+
+        assert_eq!(verdict.verdict, Verdict::Uncertain);
+        assert_eq!(verdict.fix, None);
+    }
+
+    #[test]
+    fn parse_single_verdict_falls_back_to_uncertain_on_unparseable_text() {
+        let verdict = parse_single_verdict(7, "RULE-2", "not json at all");
+
+        assert_eq!(verdict.line, 7);
+        assert_eq!(verdict.vulnerability_id, "RULE-2");
+        assert_eq!(verdict.verdict, Verdict::Uncertain);
+        assert_eq!(verdict.confidence, 0.0);
+        assert_eq!(verdict.rationale, "not json at all");
+        assert_eq!(verdict.fix, None);
+    }
+
+    #[test]
+    fn severity_parses_known_names() {
+        use std::str::FromStr;
+
+        assert_eq!(Severity::from_str("Info").unwrap(), Severity::Info);
+        assert_eq!(Severity::from_str("Critical").unwrap(), Severity::Critical);
+        assert!(Severity::from_str("Unknown").is_err());
+    }
+
+    #[test]
+    fn severity_orders_least_to_most_urgent() {
+        assert!(Severity::Info < Severity::Low);
+        assert!(Severity::Low < Severity::Medium);
+        assert!(Severity::Medium < Severity::High);
+        assert!(Severity::High < Severity::Critical);
+    }
+
+    #[test]
+    fn false_positive_justification_matches_code_not_present() {
+        assert_eq!(
+            false_positive_justification("The flagged function is not present in this file."),
+            Some("code_not_present".to_string())
+        );
+    }
+
+    #[test]
+    fn false_positive_justification_matches_code_not_reachable() {
+        assert_eq!(
+            false_positive_justification("This branch is unreachable at runtime."),
+            Some("code_not_reachable".to_string())
+        );
+    }
+
+    #[test]
+    fn false_positive_justification_falls_back_to_none() {
+        assert_eq!(
+            false_positive_justification("The input is already sanitized elsewhere."),
+            None
+        );
     }
 }